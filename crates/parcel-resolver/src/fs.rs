@@ -1,10 +1,14 @@
 use std::{
-  io::Result,
+  collections::{HashMap, HashSet},
+  io::{Error, ErrorKind, Result},
   path::{Path, PathBuf},
+  sync::Arc,
 };
 
 use bitflags::bitflags;
 
+use crate::Cache;
+
 bitflags! {
   /// Bitflags that describe path metadata.
   pub struct FileKind: u8 {
@@ -64,3 +68,182 @@ impl FileSystem for OsFileSystem {
     path.read_link()
   }
 }
+
+/// A file system that layers in-memory files on top of another file system.
+/// All files must be inserted before it is wrapped in a [Cache], since file kinds are memoized.
+pub struct OverlayFileSystem {
+  base: Arc<dyn FileSystem>,
+  files: HashMap<PathBuf, Arc<[u8]>>,
+  dirs: HashSet<PathBuf>,
+}
+
+impl OverlayFileSystem {
+  /// Creates an empty overlay on top of the given file system.
+  pub fn new(base: Arc<dyn FileSystem>) -> Self {
+    OverlayFileSystem {
+      base,
+      files: HashMap::new(),
+      dirs: HashSet::new(),
+    }
+  }
+
+  /// Adds a virtual file, replacing any previous overlay entry for the same path.
+  /// The file is also registered under its canonical path, with symbolic links in the
+  /// parent directory resolved through the base file system, since that is the path
+  /// the resolver returns.
+  pub fn insert<P: AsRef<Path>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+    let path = path.as_ref();
+    let canonical = self.canonicalize_parent(path);
+    let contents: Arc<[u8]> = contents.into().into();
+    for path in [path, canonical.as_path()] {
+      for dir in path.ancestors().skip(1) {
+        if !self.dirs.insert(dir.to_path_buf()) {
+          break;
+        }
+      }
+      self.files.insert(path.to_path_buf(), Arc::clone(&contents));
+    }
+  }
+
+  fn canonicalize_parent(&self, path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+      (Some(parent), Some(file_name)) => {
+        let cache = Cache::new(Arc::clone(&self.base));
+        match cache.get_normalized(parent).canonicalize(&cache) {
+          Ok(parent) => parent.as_path().join(file_name),
+          Err(_) => path.to_path_buf(),
+        }
+      }
+      _ => path.to_path_buf(),
+    }
+  }
+}
+
+impl FileSystem for OverlayFileSystem {
+  fn read_to_string(&self, path: &Path) -> Result<String> {
+    match self.files.get(path) {
+      Some(contents) => {
+        String::from_utf8(contents.to_vec()).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+      }
+      None => self.base.read_to_string(path),
+    }
+  }
+
+  fn kind(&self, path: &Path) -> FileKind {
+    if self.files.contains_key(path) {
+      FileKind::IS_FILE
+    } else {
+      let kind = self.base.kind(path);
+      if kind.is_empty() && self.dirs.contains(path) {
+        FileKind::IS_DIR
+      } else {
+        kind
+      }
+    }
+  }
+
+  fn read_link(&self, path: &Path) -> Result<PathBuf> {
+    if self.files.contains_key(path) {
+      Err(Error::new(ErrorKind::InvalidInput, "not a symbolic link"))
+    } else {
+      self.base.read_link(path)
+    }
+  }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+  use super::*;
+  use crate::{Resolution, Resolver, SpecifierType};
+  use assert_fs::prelude::*;
+
+  #[test]
+  fn test_overlay() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("real.js").write_str("real")?;
+    dir.child("shadowed.js").write_str("disk")?;
+
+    let mut fs = OverlayFileSystem::new(Arc::new(OsFileSystem));
+    fs.insert(dir.child("shadowed.js").path(), "overlay");
+    fs.insert(dir.child("virtual/entry.js").path(), "virtual");
+
+    assert_eq!(fs.read_to_string(dir.child("real.js").path())?, "real");
+    assert_eq!(
+      fs.read_to_string(dir.child("shadowed.js").path())?,
+      "overlay"
+    );
+    assert_eq!(
+      fs.read_to_string(dir.child("virtual/entry.js").path())?,
+      "virtual"
+    );
+    assert_eq!(fs.kind(dir.child("virtual").path()), FileKind::IS_DIR);
+    assert_eq!(
+      fs.kind(dir.child("virtual/entry.js").path()),
+      FileKind::IS_FILE
+    );
+    assert!(fs.kind(dir.child("missing.js").path()).is_empty());
+    assert!(fs.read_link(dir.child("virtual/entry.js").path()).is_err());
+
+    let resolver = Resolver::parcel(dir.path(), Cache::new(Arc::new(fs)));
+    assert_eq!(
+      resolver
+        .resolve(
+          "./virtual/entry",
+          dir.child("real.js").path(),
+          SpecifierType::Esm
+        )
+        .result?
+        .resolution,
+      Resolution::Path(dir.child("virtual/entry.js").path().to_path_buf())
+    );
+
+    #[cfg(windows)]
+    if !is_elevated::is_elevated() {
+      println!("skipping symlink tests due to missing permissions");
+      return Ok(());
+    }
+
+    // Virtual files under a symlinked directory are also stored under their canonical path,
+    // and must not hide the symlink from the cache.
+    dir.child("packages/pkg/real.js").write_str("")?;
+    dir.child("node_modules").create_dir_all()?;
+    dir
+      .child("node_modules/pkg")
+      .symlink_to_dir(dir.child("packages/pkg").path())?;
+
+    let mut fs = OverlayFileSystem::new(Arc::new(OsFileSystem));
+    fs.insert(dir.child("node_modules/pkg/virtual.js").path(), "virtual");
+    assert_eq!(
+      fs.kind(dir.child("node_modules/pkg").path()),
+      FileKind::IS_DIR | FileKind::IS_SYMLINK
+    );
+
+    let cache = Cache::new(Arc::new(fs));
+    let packages = cache
+      .get(dir.child("packages/pkg").path())
+      .canonicalize(&cache)?;
+    assert_eq!(
+      cache
+        .get(dir.child("node_modules/pkg/real.js").path())
+        .canonicalize(&cache)?,
+      cache
+        .get(dir.child("packages/pkg/real.js").path())
+        .canonicalize(&cache)?
+    );
+
+    let resolver = Resolver::parcel(dir.path(), &cache);
+    let resolution = resolver
+      .resolve(
+        "./node_modules/pkg/virtual.js",
+        dir.child("real.js").path(),
+        SpecifierType::Esm,
+      )
+      .result?
+      .resolution;
+    let virtual_path = packages.as_path().join("virtual.js");
+    assert_eq!(resolution, Resolution::Path(virtual_path.clone()));
+    assert_eq!(cache.fs.read_to_string(&virtual_path)?, "virtual");
+
+    Ok(())
+  }
+}
@@ -55,7 +55,7 @@ use cache::CachedPath;
 pub use error::ResolverError;
 #[cfg(not(target_arch = "wasm32"))]
 pub use fs::OsFileSystem;
-pub use fs::{FileKind, FileSystem};
+pub use fs::{FileKind, FileSystem, OverlayFileSystem};
 pub use invalidations::*;
 use package_json::{AliasValue, ExportsResolution, PackageJson};
 pub use package_json::{ExportsCondition, Fields, ModuleType, PackageJsonError};